@@ -0,0 +1,87 @@
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::caption::CaptionRecord;
+use crate::store::OutputType;
+
+const ABORT_WINDOW: Duration = Duration::from_secs(2);
+
+/// Saves the most recently known set of captions through a fresh store
+/// whenever Ctrl-C is triggered, so an interrupted run doesn't lose
+/// in-progress work. Ctrl-C can reach this from two different places:
+///
+/// - an OS-level SIGINT handler (`install_signal_handler`), for the
+///   phases before the cursive editor takes over the terminal
+/// - a cursive key binding, for while the editor is running - cursive
+///   puts the terminal into raw mode, which disables the kernel's
+///   Ctrl-C-to-SIGINT translation, so the signal handler never fires
+///   once the editor has started
+///
+/// Both call `trigger`, which holds the actual save-or-abort logic.
+pub struct Autosave {
+    output_type: OutputType,
+    output_path: PathBuf,
+    snapshot: Mutex<Option<Vec<CaptionRecord>>>,
+    last_interrupt: Mutex<Option<Instant>>,
+}
+
+impl Autosave {
+    /// Create an autosave with nothing to save yet. `update` should be
+    /// called as soon as a real set of captions exists.
+    pub fn new(output_type: OutputType, output_path: PathBuf) -> Autosave {
+        Autosave {
+            output_type,
+            output_path,
+            snapshot: Mutex::new(None),
+            last_interrupt: Mutex::new(None),
+        }
+    }
+
+    /// Replace the saved snapshot with the latest known captions.
+    pub fn update(&self, captions: Vec<CaptionRecord>) {
+        *self.snapshot.lock().unwrap() = Some(captions);
+    }
+
+    /// Install the OS-level SIGINT handler. Only effective before the
+    /// cursive editor starts - see the struct docs.
+    pub fn install_signal_handler(self: &Arc<Self>) {
+        let autosave = Arc::clone(self);
+
+        ctrlc::set_handler(move || {
+            if autosave.trigger() {
+                std::process::exit(130);
+            }
+        }).expect("Error: unable to install Ctrl-C handler");
+    }
+
+    /// Save the current snapshot (if there is one yet), or report that
+    /// this is a second Ctrl-C within the abort window. Returns whether
+    /// the caller should exit immediately.
+    pub fn trigger(&self) -> bool {
+        let mut last_interrupt = self.last_interrupt.lock().unwrap();
+        let now = Instant::now();
+
+        let double_tap = last_interrupt.map_or(false, |previous| now.duration_since(previous) < ABORT_WINDOW);
+
+        if double_tap {
+            return true;
+        }
+
+        *last_interrupt = Some(now);
+
+        match self.snapshot.lock().unwrap().as_ref() {
+            Some(captions) => {
+                let store = self.output_type.store();
+
+                match store.write(captions, self.output_path.as_path()) {
+                    Ok(()) => println!("interrupted - captions saved (press again to abort)"),
+                    Err(err) => eprintln!("Error: unable to autosave captions: {}", err),
+                }
+            }
+            None => println!("interrupted - nothing to save yet (press again to abort)"),
+        }
+
+        false
+    }
+}
@@ -0,0 +1,51 @@
+use std::path::PathBuf;
+
+use crate::image_format::ImageFormat;
+
+#[derive(Debug, Clone)]
+pub struct CaptionRecord {
+    /// Path to the image being captioned
+    pub image_path: PathBuf,
+
+    /// Caption for the image
+    pub caption: String,
+
+    /// The image's format, derived from its extension. `None` if the
+    /// extension isn't one this tool recognises. Used by `MetadataWriter`
+    /// to decide whether to also embed an IPTC caption (JPEG only).
+    pub format: Option<ImageFormat>,
+
+    /// Content hash of the image file as of the last time this record
+    /// was saved (computed with `ThumbnailCache::hash_file`), compared in
+    /// `main`'s merge step to flag images that have been replaced since.
+    /// `None` until a hash has been computed.
+    pub image_hash: Option<String>,
+}
+
+impl CaptionRecord {
+    /// Create a new CaptionRecord
+    pub fn new(image_path: &PathBuf, caption: String) -> CaptionRecord {
+        CaptionRecord {
+            image_path: image_path.clone(),
+            caption: caption.clone(),
+            format: ImageFormat::from_path(image_path),
+            image_hash: None,
+        }
+    }
+
+    /// Create a new empty CaptionRecord
+    pub fn empty_caption(image_path: &PathBuf) -> CaptionRecord {
+        CaptionRecord::new(image_path, String::new())
+    }
+
+    /// Get the name of the image file associated with this
+    /// CaptionRecord.
+    pub fn get_filename(&self) -> &str {
+        self.image_path.file_name().unwrap().to_str().unwrap()
+    }
+
+    /// Get a label representing this CaptionRecord.
+    pub fn get_label(&self) -> String {
+        format!("{}: {}", self.get_filename(), self.caption)
+    }
+}
@@ -0,0 +1,59 @@
+use std::path::Path;
+
+/// Image container formats this tool can scan and caption. Centralizes
+/// the extension-to-format mapping so callers don't each re-derive the
+/// image kind from a path's extension.
+///
+/// This enum only narrows candidates by extension; `main`'s gallery walk
+/// backs it with a cheap header probe before accepting a candidate (see
+/// `get_image_files`), so a file with a corrupt header or in a format the
+/// linked `image` build can't decode is skipped with a warning at scan
+/// time rather than failing later.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Jpeg,
+    Png,
+    WebP,
+    Tiff,
+    Bmp,
+    Gif,
+    Avif,
+    #[cfg(feature = "heif")]
+    Heif,
+}
+
+impl ImageFormat {
+    /// Determine the image format from a file extension (without the
+    /// leading dot, case-insensitive), or `None` if it isn't a format
+    /// this tool can decode.
+    pub fn from_extension(extension: &str) -> Option<ImageFormat> {
+        match extension.to_lowercase().as_str() {
+            "jpg" | "jpeg" => Some(ImageFormat::Jpeg),
+            "png" => Some(ImageFormat::Png),
+            "webp" => Some(ImageFormat::WebP),
+            "tif" | "tiff" => Some(ImageFormat::Tiff),
+            "bmp" => Some(ImageFormat::Bmp),
+            "gif" => Some(ImageFormat::Gif),
+            "avif" => Some(ImageFormat::Avif),
+            #[cfg(feature = "heif")]
+            "heic" | "heif" => Some(ImageFormat::Heif),
+            _ => None,
+        }
+    }
+
+    /// Determine the image format from a path's extension.
+    pub fn from_path(path: &Path) -> Option<ImageFormat> {
+        path.extension().and_then(|ext| ext.to_str()).and_then(ImageFormat::from_extension)
+    }
+
+    /// Extensions that are recognisable as an image format but aren't
+    /// currently decodable (e.g. `heic`/`heif` without the `heif` feature
+    /// enabled), so callers can warn instead of silently skipping them.
+    pub fn is_disabled_extension(extension: &str) -> bool {
+        #[cfg(feature = "heif")]
+        return false;
+
+        #[cfg(not(feature = "heif"))]
+        matches!(extension.to_lowercase().as_str(), "heic" | "heif")
+    }
+}
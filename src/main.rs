@@ -1,15 +1,31 @@
-use std::error::Error;
 use std::path::{PathBuf, Path};
-use std::{fs, env, io};
+use std::{env, io};
 use structopt::StructOpt;
-use csv;
 use cursive::Cursive;
+use cursive::event::Event;
 use cursive::views::{Dialog, SelectView, EditView, ViewRef, ScrollView};
 use cursive::traits::{Identifiable, Boxable};
-use std::cell::{RefCell, RefMut};
+use std::cell::{Cell, RefCell, RefMut};
 use std::borrow::{Borrow, BorrowMut};
 use std::rc::Rc;
 use std::process::Command;
+use std::sync::Arc;
+use walkdir::WalkDir;
+
+mod autosave;
+mod caption;
+mod image_format;
+mod metadata;
+mod shell;
+mod store;
+mod thumbnail;
+
+use autosave::Autosave;
+use caption::CaptionRecord;
+use image_format::ImageFormat;
+use metadata::MetadataWriter;
+use store::{CaptionStore, OutputType};
+use thumbnail::{ThumbnailCache, THUMBNAIL_DIR};
 
 
 #[derive(Debug, StructOpt)]
@@ -19,9 +35,9 @@ struct Opt {
     #[structopt(parse(from_os_str))]
     gallery_dir: Option<PathBuf>,
 
-    /// The type of output, available options: "csv"
+    /// The type of output, available options: "csv", "json", "xmp", "html"
     #[structopt(short = "t", long = "output-type", default_value = "csv")]
-    output_type: String,
+    output_type: OutputType,
 
     /// The name of the output file (if there is one).
     /// Will be "captions.csv" by default for the "csv" output-type.
@@ -32,6 +48,24 @@ struct Opt {
     #[structopt(short = "e", long = "edit")]
     edit: bool,
 
+    /// Embed each caption directly into its image's EXIF metadata (and,
+    /// for JPEGs, its IPTC metadata too), in addition to writing the
+    /// chosen output-type. Existing embedded captions are also read back
+    /// to pre-populate records that would otherwise start empty.
+    #[structopt(long = "embed")]
+    embed: bool,
+
+    /// Launch an interactive command shell (`pxar:>`) for navigating,
+    /// filtering and editing captions, instead of the cursive dialog editor.
+    #[structopt(long = "shell")]
+    shell: bool,
+
+    /// Walk subdirectories of the gallery too, instead of just its
+    /// top level. Captions for nested images are keyed by their path
+    /// relative to the gallery directory.
+    #[structopt(short = "r", long = "recursive")]
+    recursive: bool,
+
     /// The command used to launch an image viewer
     /// upon editing the caption for an image in order
     /// to view the image who's caption is being edited
@@ -47,41 +81,6 @@ struct Opt {
     view_command_args: Option<Vec<String>>,
 }
 
-#[derive(Debug, Clone)]
-struct CaptionRecord {
-    /// Path to the image being captioned
-    pub image_path: PathBuf,
-    
-    /// Caption for the image
-    pub caption: String,
-}
-
-impl CaptionRecord {
-    /// Create a new CaptionRecord
-    fn new(image_path: &PathBuf, caption: String) -> CaptionRecord {
-        CaptionRecord {
-            image_path: image_path.clone(),
-            caption: caption.clone(),
-        }
-    }
-
-    /// Create a new empty CaptionRecord
-    fn empty_caption(image_path: &PathBuf) -> CaptionRecord {
-        CaptionRecord::new(image_path, String::new())
-    }
-
-    /// Get the name of the image file associated with this
-    /// CaptionRecord.
-    fn get_filename(&self) -> &str {
-        self.image_path.file_name().unwrap().to_str().unwrap()
-    }
-
-    /// Get a label representing this CaptionRecord.
-    fn get_label(&self) -> String {
-        format!("{}: {}", self.get_filename(), self.caption)
-    }
-}
-
 /// A command for previewing an image, to be executed
 /// in the shell/command line.
 #[derive(Debug, Clone)]
@@ -103,89 +102,85 @@ impl ViewCommand {
     }
 }
 
+/// Whether `entry` is a directory the gallery walk should not descend
+/// into: the thumbnail cache itself, or any other dot-directory (VCS
+/// metadata, editor swap dirs, etc.), none of which hold real gallery
+/// images. The gallery root itself is never excluded even if its own
+/// name happens to start with a dot.
+fn is_excluded_dir(entry: &walkdir::DirEntry) -> bool {
+    entry.depth() > 0
+        && entry.file_type().is_dir()
+        && entry.file_name().to_str().map_or(false, |name| name == THUMBNAIL_DIR || name.starts_with('.'))
+}
+
 /// Get a Vec of paths to image files in the specified gallery_dir
-/// directory path. Or get an error if there was a problem.
-fn get_image_files(gallery_dir: &PathBuf) -> io::Result<Vec<PathBuf>> {
+/// directory path, optionally walking its subdirectories too. Or get
+/// an error if there was a problem.
+///
+/// A file's extension first narrows it to a candidate `ImageFormat`,
+/// then a cheap header probe (`image::image_dimensions`, which reads
+/// just enough to determine the format/size without decoding pixels)
+/// confirms its header is readable, so a file with a corrupt header or
+/// in a format the linked `image` build can't decode is skipped with a
+/// warning here rather than failing later at thumbnail time. Damage past
+/// the header is not detected by this probe.
+fn get_image_files(gallery_dir: &PathBuf, recursive: bool) -> io::Result<Vec<PathBuf>> {
     let mut paths: Vec<PathBuf> = Vec::new();
-    let supported_extensions = vec!["jpg", "jpeg", "png"];
 
-    for entry in fs::read_dir(gallery_dir)? {
-        let entry_path = entry?.path().clone();
+    let mut walker = WalkDir::new(gallery_dir);
+    if !recursive {
+        walker = walker.max_depth(1);
+    }
+
+    for entry in walker.into_iter().filter_entry(|entry| !is_excluded_dir(entry)) {
+        let entry = entry.map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
 
-        match entry_path.extension() {
-            Some(ext) => {
-                let ext_string = ext.to_str().expect("unable to convert path").to_lowercase();
-                let ext_str = ext_string.as_str();
+        if !entry.file_type().is_file() {
+            continue;
+        }
 
-                if supported_extensions.contains(&ext_str)
-                {
-                    paths.push(entry_path);
+        let entry_path = entry.path().to_path_buf();
+
+        match entry_path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ImageFormat::from_extension(ext).is_some() => {
+                match image::image_dimensions(&entry_path) {
+                    Ok(_) => paths.push(entry_path),
+                    Err(err) => eprintln!("Warning: skipping \"{}\", unable to decode it as an image: {}", entry_path.display(), err),
                 }
-            },
-            None => ()
+            }
+            Some(ext) if ImageFormat::is_disabled_extension(ext) => {
+                eprintln!("Warning: skipping \"{}\", support for \".{}\" is disabled in this build", entry_path.display(), ext);
+            }
+            _ => ()
         }
     }
 
     Ok(paths)
 }
 
-/// Generate a Vec of empty CaptionRecord from a Vec of image paths
-fn generate_empty_captions(image_paths: &Vec<PathBuf>) -> Vec<CaptionRecord> {
+/// Generate a Vec of CaptionRecord from a Vec of image paths. When `embed`
+/// is set, each image's EXIF metadata is checked first so a previously
+/// embedded caption pre-populates the record instead of starting empty.
+fn generate_empty_captions(image_paths: &Vec<PathBuf>, embed: bool) -> Vec<CaptionRecord> {
     let mut records: Vec<CaptionRecord> = Vec::new();
 
     for image_path in image_paths {
-        records.push(CaptionRecord::empty_caption(&image_path))
-    }
-
-    return records;
-}
-
-/// Read a CSV file which specifies captions, and create a Vec of
-/// CaptionRecord, or an Error if there was a problem doing this.
-/// csv_path is the path to where the CSV file is located.
-/// 
-/// ```csv
-/// Image Path,Caption
-/// example.jpg,This is an example caption
-/// example2.jpg,Another example
-/// ```
-fn read_caption_csv(csv_path: &Path) -> Result<Vec<CaptionRecord>, Box<dyn Error>> {
-    let image_directory = csv_path.parent().expect("csv path is not a valid file").to_path_buf();
-    let mut captions: Vec<CaptionRecord> = Vec::new();
-    let mut rdr = csv::Reader::from_path(csv_path)?;
-
-    for item in rdr.records() {
-        let record = item?;
-        let image_filename = record.get(0).expect("badly formatted image filename in csv");
-        let caption = record.get(1).expect("badly formatted caption entry in csv");
-
-        let image_path = image_directory.join(image_filename);
-
-        let caption_record = CaptionRecord::new(&image_path, caption.to_owned());
-
-        captions.push(caption_record);
-    }
-
-    return Ok(captions);
-}
-
-/// Write a Vec of CaptionRecord to a CSV file with the specified
-/// csv_path. 
-fn write_caption_csv(records: &Vec<CaptionRecord>, csv_path: &Path) -> Result<(), Box<dyn Error>> {
-    println!("Writing captions to \"{}\".", csv_path.display());
-
-    let mut wtr = csv::Writer::from_path(csv_path)?;
-    wtr.write_record(&["Image", "Caption"])?;
+        let record = if embed {
+            match MetadataWriter::read(image_path) {
+                Some(caption) => CaptionRecord::new(image_path, caption),
+                None => CaptionRecord::empty_caption(&image_path),
+            }
+        } else {
+            CaptionRecord::empty_caption(&image_path)
+        };
 
-    for record in records {
-        let image_filename: &str = record.image_path.file_name().expect("expected image to be a filename").to_str().unwrap();
-        wtr.write_record(&[image_filename, record.caption.as_str()])?;
+        records.push(record)
     }
 
-    Ok(())
+    return records;
 }
 
-/// Callback to be used when the Ok button is pressed in the 
+/// Callback to be used when the Ok button is pressed in the
 /// edit caption dialog.
 fn submit_callback(s: &mut Cursive) {
     let new_caption_text: Rc<String> = s.call_on_id("edit_caption", |view: &mut EditView| {
@@ -209,10 +204,27 @@ fn submit_callback(s: &mut Cursive) {
     s.pop_layer();
 }
 
+/// Rebuild `autosave`'s snapshot from the live `editable_captions` working
+/// set, so a later Ctrl-C always saves the latest edits rather than
+/// whatever was on screen when the editor was first opened.
+fn refresh_snapshot(editable_captions: &[Rc<RefCell<CaptionRecord>>], autosave: &Arc<Autosave>) {
+    let records: Vec<CaptionRecord> = editable_captions.iter()
+        .map(|record| RefCell::borrow(record.borrow()).clone())
+        .collect();
+
+    autosave.update(records);
+}
+
 /// Function triggered when the user wants to edit the caption
 /// for a selected image. Runs the ViewCommand (if specified by the user),
 /// and shows the edit caption dialog.
-fn edit_caption(view_command: &Option<ViewCommand>, s: &mut Cursive, record: Rc<RefCell<CaptionRecord>>) {
+fn edit_caption(
+    view_command: &Option<ViewCommand>,
+    s: &mut Cursive,
+    record: Rc<RefCell<CaptionRecord>>,
+    editable_captions: Vec<Rc<RefCell<CaptionRecord>>>,
+    autosave: Arc<Autosave>,
+) {
     let record_ref = RefCell::borrow(record.borrow());
     let caption_text = record_ref.caption.clone();
     let image_file_name = String::from(record_ref.get_filename().clone());
@@ -241,24 +253,35 @@ fn edit_caption(view_command: &Option<ViewCommand>, s: &mut Cursive, record: Rc<
     }
 
 
+    let ev_editable_captions = editable_captions.clone();
+    let ev_autosave = Arc::clone(&autosave);
+
     let mut ev = EditView::new();
     ev.set_content(caption_text);
-    ev.set_on_submit(|s, _| {
+    ev.set_on_submit(move |s, _| {
         submit_callback(s);
+        refresh_snapshot(&ev_editable_captions, &ev_autosave);
     });
 
     s.add_layer(Dialog::around(ev.with_id("edit_caption")
             .fixed_width(10))
         .title(format!("Editing caption for image {}", image_file_name))
-        .button("Ok", submit_callback)
+        .button("Ok", move |s| {
+            submit_callback(s);
+            refresh_snapshot(&editable_captions, &autosave);
+        })
         .button("Cancel", |s| {
             s.pop_layer();
         }));
 }
 
-/// Shows a command line GUI using the cursive library, for editing
-/// the captions.
-fn edit_captions(opt: &Opt, captions: &Vec<CaptionRecord>) -> Vec<CaptionRecord> {
+/// Shows a command line GUI using the cursive library, for editing the
+/// captions. Registers a Ctrl-C key binding for the duration of the editor
+/// so an interrupted session still autosaves in-progress edits - cursive
+/// puts the terminal into raw mode, which stops Ctrl-C from reaching us
+/// as a SIGINT, so `autosave`'s OS-level handler can't fire here; this
+/// binding is what actually covers the editor (see `Autosave`'s docs).
+fn edit_captions(opt: &Opt, captions: &Vec<CaptionRecord>, autosave: Arc<Autosave>) -> Vec<CaptionRecord> {
     if opt.edit == false {
         return captions.clone();
     }
@@ -269,9 +292,25 @@ fn edit_captions(opt: &Opt, captions: &Vec<CaptionRecord>) -> Vec<CaptionRecord>
         editable_captions.push(Rc::new(RefCell::new(record.clone())))
     }
 
+    autosave.update(captions.clone());
+
     // Creates the cursive root - required for every application.
     let mut siv = Cursive::default();
 
+    // `abort_requested` carries a second Ctrl-C out of the callback: we
+    // quit cursive first so it restores the terminal normally, then exit
+    // the process once `siv.run` below returns, rather than calling
+    // `process::exit` from inside the raw-mode session.
+    let abort_requested = Rc::new(Cell::new(false));
+    let cursive_autosave = Arc::clone(&autosave);
+    let cursive_abort_requested = Rc::clone(&abort_requested);
+    siv.add_global_callback(Event::CtrlChar('c'), move |s| {
+        if cursive_autosave.trigger() {
+            cursive_abort_requested.set(true);
+            s.quit();
+        }
+    });
+
     let mut select_view = SelectView::<Rc<RefCell<CaptionRecord>>>::new();
 
     for record in &editable_captions {
@@ -286,10 +325,11 @@ fn edit_captions(opt: &Opt, captions: &Vec<CaptionRecord>) -> Vec<CaptionRecord>
     };
 
     let view_command_rc = Rc::new(view_command);
+    let select_editable_captions = editable_captions.clone();
 
     select_view.set_on_submit(move |s, record: &Rc<RefCell<CaptionRecord>>| {
         let vc = view_command_rc.clone();
-        edit_caption(vc.as_ref(), s, record.clone());
+        edit_caption(vc.as_ref(), s, record.clone(), select_editable_captions.clone(), Arc::clone(&autosave));
     });
 
     // Creates a dialog with a single "Ok" button
@@ -306,6 +346,10 @@ fn edit_captions(opt: &Opt, captions: &Vec<CaptionRecord>) -> Vec<CaptionRecord>
     // Starts the event loop.
     siv.run();
 
+    if abort_requested.get() {
+        std::process::exit(130);
+    }
+
     let mut new_captions: Vec<CaptionRecord> = Vec::new();
     for record_ref in editable_captions {
         let record_ref_rc = record_ref.clone();
@@ -326,58 +370,113 @@ fn main() {
         None => env::current_dir().expect("Error: cannot get current directory")
     };
 
-    let image_paths = get_image_files(&gallery_dir).expect("Error: unable to read image files from gallery directory");
-
-    let output_type: String = opt.output_type.clone();
-    match output_type.as_str() {
-       "csv" => {
-           let csv_filename: String = match opt.output_name.clone() {
-               Some(name) => name,
-               None => String::from("captions.csv")
-           };
-
-           let csv_path = gallery_dir.join(Path::new(csv_filename.as_str()));
-
-           let mut captions = if csv_path.exists()
-           {
-               println!("Caption file \"{}\" already exists, reading file.", csv_filename);
-               let mut captions = read_caption_csv(csv_path.as_path()).expect("unable to read captions csv");
-               let mut images_with_no_cations: Vec<PathBuf> = Vec::new();
-
-               for image_path in image_paths {
-                   match captions.iter().find(|&record| {
-                       record.image_path.canonicalize().unwrap().eq(&image_path.canonicalize().unwrap())
-                   }) {
-                       Some(_record) => (),
-                       None => images_with_no_cations.push(image_path)
-                   }
-               }
-
-               let mut new_captions = generate_empty_captions(&images_with_no_cations);
-
-               println!("Appending the following new images: [{}]", new_captions.iter()
-                   .fold(String::new(), |acc, record| {
-                       acc + &record.image_path.file_name().unwrap().to_str().unwrap() + ", "
-                   }));
-
-               captions.append(&mut new_captions);
-
-               captions.sort_by(|a, b| {
-                   a.image_path.file_name().unwrap().cmp(b.image_path.file_name().unwrap())
-               });
-
-               captions
-           } else {
-               println!("Generating new captions.");
-               generate_empty_captions(&image_paths)
-           };
-
-           captions = edit_captions(&opt, &mut captions);
-
-           write_caption_csv(&captions, csv_path.as_path()).expect("unable to write captions to csv");
-       },
-        _ => println!("Error: unsupported output type {}", output_type)
+    let output_type = opt.output_type;
+    let store = output_type.store();
+
+    let output_name = opt.output_name.clone().or_else(|| output_type.default_output_name().map(String::from));
+
+    let output_path = match &output_name {
+        Some(name) => gallery_dir.join(Path::new(name.as_str())),
+        // The Xmp backend has no single aggregate file - sidecars live
+        // alongside each image in the gallery directory.
+        None => gallery_dir.clone(),
+    };
+
+    // Installed before the (potentially slow) gallery scan below, so a
+    // Ctrl-C during it is still handled instead of only once editing
+    // starts.
+    let autosave = Arc::new(Autosave::new(output_type, output_path.clone()));
+    autosave.install_signal_handler();
+
+    let image_paths = get_image_files(&gallery_dir, opt.recursive).expect("Error: unable to read image files from gallery directory");
+
+    let existing = match output_name {
+        Some(_) => output_path.exists(),
+        None => true,
+    };
+
+    let mut captions = if existing {
+        println!("Caption file \"{}\" already exists, reading file.", output_path.display());
+        let mut captions = store.read(output_path.as_path()).expect("unable to read existing captions");
+        let mut images_with_no_cations: Vec<PathBuf> = Vec::new();
+
+        for image_path in image_paths {
+            // `image_path` was just found on disk by the scan above, so it
+            // canonicalizes fine - but `record.image_path` comes from the
+            // caption file and may point at an image deleted since, which
+            // fails to canonicalize. Treat that as "no match" rather than
+            // panicking: a record for a missing image just stays as-is.
+            let canonical_image_path = image_path.canonicalize().unwrap_or_else(|_| image_path.clone());
+
+            match captions.iter_mut().find(|record| {
+                record.image_path.canonicalize().map_or(false, |path| path == canonical_image_path)
+            }) {
+                Some(record) => {
+                    if let Ok(current_hash) = ThumbnailCache::hash_file(&record.image_path) {
+                        if let Some(previous_hash) = &record.image_hash {
+                            if previous_hash != &current_hash {
+                                println!("Warning: \"{}\" has changed since its caption was last saved.", record.get_filename());
+                            }
+                        }
+
+                        record.image_hash = Some(current_hash);
+                    }
+                }
+                None => images_with_no_cations.push(image_path)
+            }
+        }
+
+        let mut new_captions = generate_empty_captions(&images_with_no_cations, opt.embed);
+
+        for record in &mut new_captions {
+            if let Ok(hash) = ThumbnailCache::hash_file(&record.image_path) {
+                record.image_hash = Some(hash);
+            }
+        }
+
+        println!("Appending the following new images: [{}]", new_captions.iter()
+            .fold(String::new(), |acc, record| {
+                acc + &record.image_path.file_name().unwrap().to_str().unwrap() + ", "
+            }));
+
+        captions.append(&mut new_captions);
+
+        captions.sort_by(|a, b| {
+            a.image_path.file_name().unwrap().cmp(b.image_path.file_name().unwrap())
+        });
+
+        captions
+    } else {
+        println!("Generating new captions.");
+        generate_empty_captions(&image_paths, opt.embed)
+    };
+
+    autosave.update(captions.clone());
+
+    captions = if opt.shell {
+        shell::run(gallery_dir.clone(), opt.recursive, store.as_ref(), output_path.clone(), captions)
+    } else {
+        edit_captions(&opt, &mut captions, Arc::clone(&autosave))
+    };
+
+    if opt.embed {
+        for record in &mut captions {
+            match MetadataWriter::write(record) {
+                // Embedding rewrites the image's own bytes, so the hash
+                // taken earlier is now stale - refresh it, or the next run
+                // would report this image as "changed" against its own
+                // freshly-saved caption.
+                Ok(()) => {
+                    if let Ok(hash) = ThumbnailCache::hash_file(&record.image_path) {
+                        record.image_hash = Some(hash);
+                    }
+                }
+                Err(err) => eprintln!("Error: unable to embed caption for \"{}\": {}", record.image_path.display(), err),
+            }
+        }
     }
+
+    store.write(&captions, output_path.as_path()).expect("unable to write captions");
 }
 
 
@@ -0,0 +1,111 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use img_parts::jpeg::Jpeg;
+use img_parts::{Bytes, Segment};
+use little_exif::exif_tag::ExifTag;
+use little_exif::metadata::Metadata;
+
+use crate::caption::CaptionRecord;
+use crate::image_format::ImageFormat;
+
+/// JPEG "Photoshop" marker, which IPTC-IIM data travels in.
+const APP13_MARKER: u8 = 0xED;
+
+/// Embeds and reads captions directly in an image's own metadata, so the
+/// caption travels with the photo file itself rather than living only in
+/// a sidecar or aggregate caption file. Only rewrites the metadata
+/// segments of the file - the raw image payload is left untouched.
+///
+/// Written to two places, since different tools read one or the other:
+/// EXIF `ImageDescription` (0x010E), via `little_exif`, on any image format
+/// `little_exif` can edit (for other formats `write` returns its error);
+/// and IPTC `Caption/Abstract` (2:120), via a
+/// raw Photoshop resource block, for JPEGs only - IPTC-IIM has no defined
+/// container outside JPEG/TIFF, and the JPEG segment editing used here
+/// only applies to JPEGs.
+pub struct MetadataWriter;
+
+impl MetadataWriter {
+    /// Embed `record`'s caption into its image's EXIF `ImageDescription`
+    /// (0x010E) field, and, for JPEGs (per `record.format`), its IPTC
+    /// `Caption/Abstract` (2:120) field too.
+    pub fn write(record: &CaptionRecord) -> Result<(), Box<dyn Error>> {
+        let mut metadata = Metadata::new_from_path(&record.image_path)?;
+
+        metadata.set_tag(ExifTag::ImageDescription(record.caption.clone()));
+
+        metadata.write_to_file(&record.image_path)?;
+
+        if record.format == Some(ImageFormat::Jpeg) {
+            write_iptc_caption(&record.image_path, &record.caption)?;
+        }
+
+        Ok(())
+    }
+
+    /// Read a previously embedded caption back out of `image_path`'s EXIF
+    /// `ImageDescription` field, if present.
+    pub fn read(image_path: &Path) -> Option<String> {
+        let metadata = Metadata::new_from_path(image_path).ok()?;
+
+        metadata.get_tag(&ExifTag::ImageDescription(String::new())).find_map(|tag| match tag {
+            ExifTag::ImageDescription(text) => Some(text.clone()),
+            _ => None,
+        })
+    }
+}
+
+/// Write `caption` into `image_path`'s IPTC `Caption/Abstract` (2:120)
+/// field by replacing its APP13 ("Photoshop") segment outright - any
+/// other Photoshop resource blocks the file had (e.g. colour profiles
+/// set elsewhere) are not preserved.
+fn write_iptc_caption(image_path: &Path, caption: &str) -> Result<(), Box<dyn Error>> {
+    let bytes = fs::read(image_path)?;
+    let mut jpeg = Jpeg::from_bytes(Bytes::from(bytes))?;
+
+    let segments = jpeg.segments_mut();
+    segments.retain(|segment| segment.marker() != APP13_MARKER);
+    segments.insert(0, Segment::new_with_contents(APP13_MARKER, Bytes::from(encode_photoshop_app13(caption))));
+
+    let mut encoded = Vec::new();
+    jpeg.encoder().write_to(&mut encoded)?;
+    fs::write(image_path, encoded)?;
+
+    Ok(())
+}
+
+/// Build the contents of a JPEG APP13 ("Photoshop") marker segment
+/// holding a single Image Resource Block with an IPTC-IIM
+/// `Caption/Abstract` (2:120) data set, in the layout Photoshop/Bridge
+/// and other IPTC-IIM readers expect.
+fn encode_photoshop_app13(caption: &str) -> Vec<u8> {
+    let caption_bytes = caption.as_bytes();
+
+    // IPTC-IIM DataSet: tag marker, record 2 ("Application Record"),
+    // dataset 120 ("Caption/Abstract"), a 2-byte big-endian length, then
+    // the caption bytes themselves.
+    let mut iim = vec![0x1C, 2, 120];
+    iim.extend_from_slice(&(caption_bytes.len() as u16).to_be_bytes());
+    iim.extend_from_slice(caption_bytes);
+
+    // Image Resource Block: "8BIM" signature, resource ID 0x0404
+    // ("IPTC-NAA record"), an empty Pascal-string name (padded to an
+    // even length), a 4-byte big-endian data size, then the IIM data
+    // itself, also padded to an even length.
+    let mut irb = Vec::new();
+    irb.extend_from_slice(b"8BIM");
+    irb.extend_from_slice(&0x0404u16.to_be_bytes());
+    irb.extend_from_slice(&[0x00, 0x00]);
+    irb.extend_from_slice(&(iim.len() as u32).to_be_bytes());
+    irb.extend_from_slice(&iim);
+    if iim.len() % 2 != 0 {
+        irb.push(0x00);
+    }
+
+    let mut app13 = Vec::new();
+    app13.extend_from_slice(b"Photoshop 3.0\0");
+    app13.extend_from_slice(&irb);
+    app13
+}
@@ -0,0 +1,186 @@
+use std::path::{Path, PathBuf};
+
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+
+use crate::caption::CaptionRecord;
+use crate::get_image_files;
+use crate::store::CaptionStore;
+
+const COMMANDS: &[&str] = &["ls", "cd", "filter", "edit", "next", "prev", "save", "quit"];
+
+/// Suggests completions for shell commands, image filenames (for `edit`)
+/// and subdirectories (for `cd`).
+struct ShellCompleter {
+    gallery_dir: PathBuf,
+}
+
+impl Completer for ShellCompleter {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let prefix = &line[..pos];
+        let word_start = prefix.rfind(' ').map(|i| i + 1).unwrap_or(0);
+        let word = &prefix[word_start..];
+
+        let candidates = if word_start == 0 {
+            COMMANDS
+                .iter()
+                .filter(|cmd| cmd.starts_with(word))
+                .map(|cmd| Pair { display: cmd.to_string(), replacement: cmd.to_string() })
+                .collect()
+        } else {
+            let dirs_only = prefix[..word_start].trim() == "cd";
+            directory_entries(&self.gallery_dir, word, dirs_only)
+        };
+
+        Ok((word_start, candidates))
+    }
+}
+
+impl Helper for ShellCompleter {}
+impl Highlighter for ShellCompleter {}
+impl Validator for ShellCompleter {}
+impl Hinter for ShellCompleter {
+    type Hint = String;
+}
+
+fn directory_entries(gallery_dir: &Path, word: &str, dirs_only: bool) -> Vec<Pair> {
+    let entries = match std::fs::read_dir(gallery_dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    entries
+        .flatten()
+        .filter(|entry| !dirs_only || entry.file_type().map_or(false, |file_type| file_type.is_dir()))
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|name| name.starts_with(word))
+        .map(|name| Pair { display: name.clone(), replacement: name })
+        .collect()
+}
+
+/// Run the interactive `pxar:>` command shell over `captions`, returning
+/// the working set once the user runs `quit` (or closes the shell with
+/// Ctrl-D). `save` writes through `store` immediately; the caller is still
+/// expected to do a final write with the returned captions afterwards.
+pub fn run(gallery_dir: PathBuf, recursive: bool, store: &dyn CaptionStore, output_path: PathBuf, mut captions: Vec<CaptionRecord>) -> Vec<CaptionRecord> {
+    let mut gallery_dir = gallery_dir;
+    let mut filter: Option<String> = None;
+    let mut cursor: usize = 0;
+
+    let mut rl: Editor<ShellCompleter> = Editor::new();
+    rl.set_helper(Some(ShellCompleter { gallery_dir: gallery_dir.clone() }));
+
+    loop {
+        let visible = visible_indices(&captions, &filter);
+
+        let line = match rl.readline("pxar:> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => break,
+            Err(err) => {
+                eprintln!("Error: {}", err);
+                break;
+            }
+        };
+
+        rl.add_history_entry(line.as_str());
+
+        let mut parts = line.trim().splitn(2, ' ');
+        let command = parts.next().unwrap_or("");
+        let argument = parts.next().unwrap_or("").trim();
+
+        match command {
+            "" => (),
+            "ls" => {
+                for (position, &idx) in visible.iter().enumerate() {
+                    let marker = if position == cursor { "*" } else { " " };
+                    println!("{} [{}] {}", marker, position, captions[idx].get_label());
+                }
+            }
+            "cd" => {
+                if argument.is_empty() {
+                    println!("usage: cd <dir>");
+                    continue;
+                }
+
+                let new_dir = gallery_dir.join(argument);
+
+                match get_image_files(&new_dir, recursive) {
+                    Ok(image_paths) => {
+                        for image_path in image_paths {
+                            let already_known = captions.iter().any(|record| record.image_path == image_path);
+
+                            if !already_known {
+                                captions.push(CaptionRecord::empty_caption(&image_path));
+                            }
+                        }
+
+                        gallery_dir = new_dir;
+                        rl.set_helper(Some(ShellCompleter { gallery_dir: gallery_dir.clone() }));
+                        cursor = 0;
+                    }
+                    Err(err) => println!("Error: unable to read \"{}\": {}", new_dir.display(), err),
+                }
+            }
+            "filter" => {
+                filter = if argument.is_empty() { None } else { Some(argument.to_lowercase()) };
+                cursor = 0;
+            }
+            "edit" => {
+                let index: Option<usize> = if argument.is_empty() {
+                    Some(cursor)
+                } else {
+                    // An index into the listing, or a filename as offered by
+                    // tab-completion.
+                    argument.parse().ok().or_else(|| {
+                        visible.iter().position(|&idx| captions[idx].get_filename() == argument)
+                    })
+                };
+
+                match index.and_then(|position| visible.get(position)) {
+                    Some(&idx) => match rl.readline(&format!("caption for \"{}\" [{}]> ", captions[idx].get_filename(), captions[idx].caption)) {
+                        Ok(new_caption) if !new_caption.is_empty() => captions[idx].caption = new_caption,
+                        _ => (),
+                    },
+                    None => println!("Error: no image at index or with filename \"{}\"", argument),
+                }
+            }
+            "next" => {
+                if cursor + 1 < visible.len() {
+                    cursor += 1;
+                }
+            }
+            "prev" => cursor = cursor.saturating_sub(1),
+            "save" => {
+                if let Err(err) = store.write(&captions, output_path.as_path()) {
+                    println!("Error: unable to save captions: {}", err);
+                }
+            }
+            "quit" => break,
+            _ => println!("Error: unknown command \"{}\"", command),
+        }
+    }
+
+    captions
+}
+
+/// Indices into `captions` whose filename or caption matches `filter`
+/// (case-insensitive substring), or every index when there's no filter.
+fn visible_indices(captions: &[CaptionRecord], filter: &Option<String>) -> Vec<usize> {
+    captions
+        .iter()
+        .enumerate()
+        .filter(|(_, record)| match filter {
+            Some(substr) => {
+                record.get_filename().to_lowercase().contains(substr) || record.caption.to_lowercase().contains(substr)
+            }
+            None => true,
+        })
+        .map(|(idx, _)| idx)
+        .collect()
+}
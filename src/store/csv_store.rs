@@ -0,0 +1,59 @@
+use std::error::Error;
+use std::path::Path;
+
+use super::CaptionStore;
+use crate::caption::CaptionRecord;
+
+/// Reads and writes captions as a CSV file with an optional trailing hash
+/// column, used to detect images replaced since their caption was saved:
+///
+/// ```csv
+/// Image,Caption,Hash
+/// example.jpg,This is an example caption,b4f3...
+/// example2.jpg,Another example,
+/// ```
+pub struct CsvStore;
+
+impl CaptionStore for CsvStore {
+    fn read(&self, path: &Path) -> Result<Vec<CaptionRecord>, Box<dyn Error>> {
+        let image_directory = path.parent().expect("csv path is not a valid file").to_path_buf();
+        let mut captions: Vec<CaptionRecord> = Vec::new();
+        let mut rdr = csv::Reader::from_path(path)?;
+
+        for item in rdr.records() {
+            let record = item?;
+            let image_filename = record.get(0).expect("badly formatted image filename in csv");
+            let caption = record.get(1).expect("badly formatted caption entry in csv");
+            let hash = record.get(2).filter(|hash| !hash.is_empty());
+
+            let image_path = image_directory.join(image_filename);
+
+            let mut caption_record = CaptionRecord::new(&image_path, caption.to_owned());
+            caption_record.image_hash = hash.map(String::from);
+
+            captions.push(caption_record);
+        }
+
+        Ok(captions)
+    }
+
+    fn write(&self, records: &[CaptionRecord], path: &Path) -> Result<(), Box<dyn Error>> {
+        println!("Writing captions to \"{}\".", path.display());
+
+        let image_directory = path.parent().expect("csv path is not a valid file");
+        let mut wtr = csv::Writer::from_path(path)?;
+        wtr.write_record(&["Image", "Caption", "Hash"])?;
+
+        for record in records {
+            // Relative to the csv's own directory, so images in nested
+            // subdirectories (from `--recursive`) still round-trip to a
+            // unique, unambiguous key instead of colliding on filename.
+            let key = record.image_path.strip_prefix(image_directory).unwrap_or(&record.image_path);
+            let hash = record.image_hash.as_deref().unwrap_or("");
+
+            wtr.write_record(&[key.to_str().expect("image path is not valid UTF-8"), record.caption.as_str(), hash])?;
+        }
+
+        Ok(())
+    }
+}
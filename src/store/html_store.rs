@@ -0,0 +1,125 @@
+use std::error::Error;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use super::CaptionStore;
+use crate::caption::CaptionRecord;
+use crate::thumbnail::ThumbnailCache;
+
+/// `id` of the `<script>` tag `write` embeds the caption data in, and
+/// `read` looks for to round-trip it.
+const CAPTION_DATA_ID: &str = "pxar-captions";
+
+/// On-disk shape of a single entry in the caption data embedded in the
+/// page, same idea as the CSV/JSON backends' own record shape.
+#[derive(Serialize, Deserialize)]
+struct HtmlRecord {
+    image: String,
+    caption: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    hash: Option<String>,
+}
+
+/// Writes a `gallery.html` contact sheet: every image's thumbnail linking
+/// to the full image, with its caption rendered beneath, for sharing a
+/// preview of the whole captioned set. The same data also goes into a
+/// JSON `<script>` tag in the page's `<head>`, invisible when viewing the
+/// page, so a later run with `-t html` can still read captions back.
+pub struct HtmlStore;
+
+impl CaptionStore for HtmlStore {
+    fn read(&self, path: &Path) -> Result<Vec<CaptionRecord>, Box<dyn Error>> {
+        let image_directory = path.parent().expect("html path is not a valid file").to_path_buf();
+        let contents = fs::read_to_string(path)?;
+
+        let start_tag = format!("<script type=\"application/json\" id=\"{}\">", CAPTION_DATA_ID);
+
+        let data_start = match contents.find(&start_tag) {
+            Some(index) => index + start_tag.len(),
+            None => return Ok(Vec::new()),
+        };
+
+        let data_end = contents[data_start..].find("</script>").ok_or("malformed gallery.html: caption data script tag is not closed")?;
+
+        let json = contents[data_start..data_start + data_end].replace("<\\/", "</");
+        let html_records: Vec<HtmlRecord> = serde_json::from_str(&json)?;
+
+        Ok(html_records
+            .into_iter()
+            .map(|record| {
+                let mut caption_record = CaptionRecord::new(&image_directory.join(record.image), record.caption);
+                caption_record.image_hash = record.hash;
+                caption_record
+            })
+            .collect())
+    }
+
+    fn write(&self, records: &[CaptionRecord], path: &Path) -> Result<(), Box<dyn Error>> {
+        println!("Writing contact sheet to \"{}\".", path.display());
+
+        let gallery_dir = path.parent().expect("html path is not a valid file");
+        let thumbnails = ThumbnailCache::new(gallery_dir);
+
+        let mut figures = String::new();
+        let mut html_records: Vec<HtmlRecord> = Vec::new();
+
+        for record in records {
+            // Pushed unconditionally, even if the thumbnail below fails:
+            // the embedded JSON is this image's only persistence with the
+            // html backend, so losing the thumbnail must never lose the
+            // caption along with it.
+            let key = record.image_path.strip_prefix(gallery_dir).unwrap_or(&record.image_path);
+
+            html_records.push(HtmlRecord {
+                image: key.to_str().expect("image path is not valid UTF-8").to_owned(),
+                caption: record.caption.clone(),
+                hash: record.image_hash.clone(),
+            });
+
+            let thumbnail_path = match thumbnails.get_or_generate(&record.image_path) {
+                Ok(thumbnail_path) => thumbnail_path,
+                Err(err) => {
+                    eprintln!("Warning: omitting \"{}\" from contact sheet, unable to generate thumbnail: {}", record.get_filename(), err);
+                    continue;
+                }
+            };
+
+            write!(
+                figures,
+                "<figure><a href=\"{image}\"><img src=\"{thumbnail}\" loading=\"lazy\"></a><figcaption>{caption}</figcaption></figure>\n",
+                image = html_escape(&relative_href(gallery_dir, &record.image_path)),
+                thumbnail = html_escape(&relative_href(gallery_dir, &thumbnail_path)),
+                caption = html_escape(&record.caption),
+            )?;
+        }
+
+        // `</` can't appear inside a `<script>` body without ending it
+        // early, so escape it the same way browsers expect.
+        let caption_data = serde_json::to_string(&html_records)?.replace("</", "<\\/");
+
+        let html = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Gallery</title>\n<style>\nbody {{ font-family: sans-serif; display: flex; flex-wrap: wrap; gap: 1em; }}\nfigure {{ margin: 0; width: 256px; }}\nimg {{ max-width: 100%; }}\n</style>\n<script type=\"application/json\" id=\"{data_id}\">{data}</script>\n</head>\n<body>\n{figures}</body>\n</html>\n",
+            data_id = CAPTION_DATA_ID,
+            data = caption_data,
+            figures = figures,
+        );
+
+        fs::write(path, html)?;
+
+        Ok(())
+    }
+}
+
+/// `path` relative to `gallery_dir`, as a forward-slash href suitable for
+/// an HTML page living in `gallery_dir` itself.
+fn relative_href(gallery_dir: &Path, path: &Path) -> String {
+    path.strip_prefix(gallery_dir).unwrap_or(path).to_string_lossy().replace('\\', "/")
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
@@ -0,0 +1,68 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use super::CaptionStore;
+use crate::caption::CaptionRecord;
+
+/// On-disk shape of a single entry in the JSON captions file. Kept separate
+/// from `CaptionRecord` so the absolute `image_path` never leaks into the
+/// serialized form - only the path relative to the gallery does, same as
+/// the CSV backend.
+#[derive(Serialize, Deserialize)]
+struct JsonRecord {
+    image: String,
+    caption: String,
+
+    /// Content hash of the image as of when this record was saved, used
+    /// to detect images replaced since. Absent from files written before
+    /// this field existed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    hash: Option<String>,
+}
+
+/// Reads and writes captions as a JSON array of `{"image", "caption"}`
+/// objects, for galleries feeding tools that consume JSON instead of CSV.
+pub struct JsonStore;
+
+impl CaptionStore for JsonStore {
+    fn read(&self, path: &Path) -> Result<Vec<CaptionRecord>, Box<dyn Error>> {
+        let image_directory = path.parent().expect("json path is not a valid file").to_path_buf();
+        let contents = fs::read_to_string(path)?;
+        let json_records: Vec<JsonRecord> = serde_json::from_str(&contents)?;
+
+        Ok(json_records
+            .into_iter()
+            .map(|record| {
+                let mut caption_record = CaptionRecord::new(&image_directory.join(record.image), record.caption);
+                caption_record.image_hash = record.hash;
+                caption_record
+            })
+            .collect())
+    }
+
+    fn write(&self, records: &[CaptionRecord], path: &Path) -> Result<(), Box<dyn Error>> {
+        println!("Writing captions to \"{}\".", path.display());
+
+        let image_directory = path.parent().expect("json path is not a valid file");
+
+        let json_records: Vec<JsonRecord> = records
+            .iter()
+            .map(|record| {
+                let key = record.image_path.strip_prefix(image_directory).unwrap_or(&record.image_path);
+
+                JsonRecord {
+                    image: key.to_str().expect("image path is not valid UTF-8").to_owned(),
+                    caption: record.caption.clone(),
+                    hash: record.image_hash.clone(),
+                }
+            })
+            .collect();
+
+        fs::write(path, serde_json::to_string_pretty(&json_records)?)?;
+
+        Ok(())
+    }
+}
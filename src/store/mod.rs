@@ -0,0 +1,77 @@
+mod csv_store;
+mod html_store;
+mod json_store;
+mod xmp_store;
+
+pub use csv_store::CsvStore;
+pub use html_store::HtmlStore;
+pub use json_store::JsonStore;
+pub use xmp_store::XmpSidecarStore;
+
+use std::error::Error;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::caption::CaptionRecord;
+
+/// A backend capable of reading and writing a gallery's captions in some
+/// on-disk format. The Ctrl-C autosave does not share a store with `main`:
+/// it builds its own from the `OutputType` whenever it saves.
+pub trait CaptionStore: Send {
+    /// Read captions back from `path`, producing the records that were
+    /// previously written by this store.
+    fn read(&self, path: &Path) -> Result<Vec<CaptionRecord>, Box<dyn Error>>;
+
+    /// Write `records` out to `path` in this store's format.
+    fn write(&self, records: &[CaptionRecord], path: &Path) -> Result<(), Box<dyn Error>>;
+}
+
+/// The available caption output backends, selected with `--output-type`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputType {
+    Csv,
+    Json,
+    Xmp,
+    Html,
+}
+
+impl OutputType {
+    /// The default output filename for this backend, for backends that
+    /// keep captions in a single aggregate file. The Xmp backend writes
+    /// one sidecar per image instead, so it has no single default name.
+    pub fn default_output_name(&self) -> Option<&'static str> {
+        match self {
+            OutputType::Csv => Some("captions.csv"),
+            OutputType::Json => Some("captions.json"),
+            OutputType::Xmp => None,
+            OutputType::Html => Some("gallery.html"),
+        }
+    }
+
+    /// Construct the `CaptionStore` implementation for this backend.
+    pub fn store(&self) -> Box<dyn CaptionStore> {
+        match self {
+            OutputType::Csv => Box::new(CsvStore),
+            OutputType::Json => Box::new(JsonStore),
+            OutputType::Xmp => Box::new(XmpSidecarStore),
+            OutputType::Html => Box::new(HtmlStore),
+        }
+    }
+}
+
+impl FromStr for OutputType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "csv" => Ok(OutputType::Csv),
+            "json" => Ok(OutputType::Json),
+            "xmp" => Ok(OutputType::Xmp),
+            "html" => Ok(OutputType::Html),
+            _ => Err(format!(
+                "unsupported output type \"{}\", expected one of: csv, json, xmp, html",
+                s
+            )),
+        }
+    }
+}
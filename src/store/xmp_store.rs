@@ -0,0 +1,105 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::CaptionStore;
+use crate::caption::CaptionRecord;
+
+const XMP_TEMPLATE: &str = r#"<?xpacket begin="" id="W5M0MpCehiHzreSzNTczkc9d"?>
+<x:xmpmeta xmlns:x="adobe:ns:meta/">
+  <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+    <rdf:Description rdf:about=""
+        xmlns:dc="http://purl.org/dc/elements/1.1/">
+      <dc:description>
+        <rdf:Alt>
+          <rdf:li xml:lang="x-default">{caption}</rdf:li>
+        </rdf:Alt>
+      </dc:description>
+    </rdf:Description>
+  </rdf:RDF>
+</x:xmpmeta>
+<?xpacket end="w"?>
+"#;
+
+/// Writes one `<image>.<ext>.xmp` sidecar per image containing a
+/// `dc:description` field, for galleries feeding web galleries and photo
+/// managers that read XMP metadata directly rather than a single
+/// aggregate caption file. The sidecar appends ".xmp" to the image's full
+/// filename rather than replacing its extension, so e.g. "photo.jpg" and
+/// "photo.png" in the same gallery get distinct sidecars instead of
+/// colliding on "photo.xmp".
+pub struct XmpSidecarStore;
+
+impl CaptionStore for XmpSidecarStore {
+    /// `gallery_dir` is scanned for `*.xmp` sidecars; each is matched back
+    /// to the image it describes by stripping its own ".xmp" suffix.
+    fn read(&self, gallery_dir: &Path) -> Result<Vec<CaptionRecord>, Box<dyn Error>> {
+        let mut captions: Vec<CaptionRecord> = Vec::new();
+
+        for entry in fs::read_dir(gallery_dir)? {
+            let sidecar_path = entry?.path();
+
+            if sidecar_path.extension().and_then(|ext| ext.to_str()) != Some("xmp") {
+                continue;
+            }
+
+            let image_path = match sidecar_image_path(gallery_dir, &sidecar_path) {
+                Some(image_path) => image_path,
+                None => continue,
+            };
+
+            let contents = fs::read_to_string(&sidecar_path)?;
+            let caption = extract_description(&contents).unwrap_or_default();
+
+            captions.push(CaptionRecord::new(&image_path, caption));
+        }
+
+        Ok(captions)
+    }
+
+    /// `path` is unused - sidecars are written alongside each image rather
+    /// than to a single aggregate location.
+    fn write(&self, records: &[CaptionRecord], _path: &Path) -> Result<(), Box<dyn Error>> {
+        for record in records {
+            let mut sidecar_name = record.image_path.file_name().expect("image path has no filename").to_os_string();
+            sidecar_name.push(".xmp");
+            let sidecar_path = record.image_path.with_file_name(sidecar_name);
+
+            println!("Writing caption to \"{}\".", sidecar_path.display());
+
+            let xmp = XMP_TEMPLATE.replace("{caption}", &escape_xml(&record.caption));
+            fs::write(sidecar_path, xmp)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Recover the image a sidecar (e.g. `photo.jpg.xmp`) describes. Sidecars
+/// are named `<image filename>.xmp`, so this is just the filename with
+/// its own ".xmp" suffix stripped back off - no directory scan needed,
+/// and no ambiguity between images that share a filename stem.
+fn sidecar_image_path(gallery_dir: &Path, sidecar_path: &Path) -> Option<PathBuf> {
+    let sidecar_name = sidecar_path.file_name()?.to_str()?;
+    let image_name = sidecar_name.strip_suffix(".xmp")?;
+
+    Some(gallery_dir.join(image_name))
+}
+
+/// Pull the `dc:description` text back out of a sidecar's XML, for the
+/// simple single-language form this store writes.
+fn extract_description(xmp: &str) -> Option<String> {
+    let tag_start = xmp.find("<rdf:li")?;
+    let content_start = xmp[tag_start..].find('>')? + tag_start + 1;
+    let content_end = xmp[content_start..].find("</rdf:li>")? + content_start;
+
+    Some(unescape_xml(&xmp[content_start..content_end]))
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+fn unescape_xml(text: &str) -> String {
+    text.replace("&lt;", "<").replace("&gt;", ">").replace("&amp;", "&")
+}
@@ -0,0 +1,59 @@
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use image::imageops::FilterType;
+
+/// Name of the thumbnail cache directory within a gallery, also used by
+/// `main`'s gallery walk to avoid re-ingesting cached thumbnails as
+/// images on a later run.
+pub(crate) const THUMBNAIL_DIR: &str = ".thumbnails";
+const THUMBNAIL_MAX_DIMENSION: u32 = 256;
+
+/// Content-hashed thumbnail cache for a gallery. Thumbnails are stored
+/// under `<gallery>/.thumbnails/<hash>.jpg`, keyed by a BLAKE3 hash of the
+/// source image's bytes, so re-running over an unchanged gallery reuses
+/// the cached file instead of re-encoding every image.
+pub struct ThumbnailCache {
+    thumbnail_dir: PathBuf,
+}
+
+impl ThumbnailCache {
+    pub fn new(gallery_dir: &Path) -> ThumbnailCache {
+        ThumbnailCache { thumbnail_dir: gallery_dir.join(THUMBNAIL_DIR) }
+    }
+
+    /// Hash of `image_path`'s current file contents, used both as the
+    /// thumbnail cache key and, by callers, to detect when an image has
+    /// been replaced since its caption was last saved.
+    pub fn hash_file(image_path: &Path) -> io::Result<String> {
+        let bytes = fs::read(image_path)?;
+        Ok(blake3::hash(&bytes).to_hex().to_string())
+    }
+
+    /// The path a thumbnail for `hash` would live at, whether or not it
+    /// has been generated yet.
+    fn thumbnail_path(&self, hash: &str) -> PathBuf {
+        self.thumbnail_dir.join(format!("{}.jpg", hash))
+    }
+
+    /// Return the cached thumbnail for `image_path`, generating and
+    /// caching it first if its content hash isn't already on disk.
+    pub fn get_or_generate(&self, image_path: &Path) -> Result<PathBuf, Box<dyn Error>> {
+        let hash = Self::hash_file(image_path)?;
+        let thumbnail_path = self.thumbnail_path(&hash);
+
+        if thumbnail_path.exists() {
+            return Ok(thumbnail_path);
+        }
+
+        fs::create_dir_all(&self.thumbnail_dir)?;
+
+        let image = image::open(image_path)?;
+        let thumbnail = image.resize(THUMBNAIL_MAX_DIMENSION, THUMBNAIL_MAX_DIMENSION, FilterType::Lanczos3);
+        thumbnail.save_with_format(&thumbnail_path, image::ImageFormat::Jpeg)?;
+
+        Ok(thumbnail_path)
+    }
+}